@@ -0,0 +1,104 @@
+// Copyright (c) 2017 Fabian Schuiki
+
+//! A generic dependency-tracking layer for incremental, memoizing query
+//! engines such as the VHDL scoreboard. While a query executes it may read
+//! other queries (e.g. `make`, `hir`, or `ast` calls that recurse); recording
+//! those reads here lets a single `invalidate` call transitively evict every
+//! node that, directly or indirectly, depended on a node that changed,
+//! instead of rebuilding the whole query cache from scratch.
+//!
+//! A query engine is expected to hold the guard returned by `enter` for the
+//! duration of a query (or check `is_verified` first to skip a query whose
+//! cached value is still good) and call `record` whenever it reads another
+//! node.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+
+/// Tracks which queries depend on which, and which queries are still
+/// verified for the current revision.
+pub struct DepGraph<K: Eq + Hash + Clone> {
+	/// Maps a node to the set of nodes that read it while they were being
+	/// computed, i.e. its dependents.
+	dependents: RefCell<HashMap<K, HashSet<K>>>,
+	/// The stack of queries currently executing, innermost last. `record`
+	/// adds an edge from the node on top of the stack to whatever it reads.
+	stack: RefCell<Vec<K>>,
+	/// The set of nodes whose cached value is known to still be valid.
+	verified: RefCell<HashSet<K>>,
+}
+
+impl<K: Eq + Hash + Clone> DepGraph<K> {
+	/// Create an empty dependency graph.
+	pub fn new() -> DepGraph<K> {
+		DepGraph {
+			dependents: RefCell::new(HashMap::new()),
+			stack: RefCell::new(Vec::new()),
+			verified: RefCell::new(HashSet::new()),
+		}
+	}
+
+	/// Mark the start of `node`'s computation, returning a guard that marks
+	/// it verified and pops it back off the query stack when dropped. Using
+	/// a guard rather than requiring a paired `leave` call means a query
+	/// that bails out early via `?` still leaves the stack balanced.
+	pub fn enter(&self, node: K) -> Enter<K> {
+		self.stack.borrow_mut().push(node);
+		Enter(self)
+	}
+
+	fn leave(&self) {
+		if let Some(node) = self.stack.borrow_mut().pop() {
+			self.verified.borrow_mut().insert(node);
+		}
+	}
+
+	/// Record that the query currently executing reads `dep`. Call this
+	/// from within `make`/`hir`/`ast` whenever they recurse into another
+	/// query.
+	pub fn record(&self, dep: K) {
+		if let Some(reader) = self.stack.borrow().last() {
+			self.dependents.borrow_mut().entry(dep).or_insert_with(HashSet::new).insert(reader.clone());
+		}
+	}
+
+	/// Returns whether `node`'s cached value is still verified, i.e. has not
+	/// been invalidated since it was last computed. A query engine can use
+	/// this to skip recomputing a node and reuse its cached value as-is.
+	pub fn is_verified(&self, node: &K) -> bool {
+		self.verified.borrow().contains(node)
+	}
+
+	/// Invalidate `node` and transitively every node that, directly or
+	/// indirectly, depends on it. Returns the full set of invalidated nodes
+	/// so the caller can evict them from its own arenas/caches; nodes not
+	/// in the returned set are left untouched.
+	pub fn invalidate(&self, node: K) -> HashSet<K> {
+		let mut evicted = HashSet::new();
+		let mut worklist = vec![node];
+		while let Some(n) = worklist.pop() {
+			if !evicted.insert(n.clone()) {
+				continue;
+			}
+			self.verified.borrow_mut().remove(&n);
+			if let Some(readers) = self.dependents.borrow_mut().remove(&n) {
+				worklist.extend(readers);
+			}
+		}
+		evicted
+	}
+}
+
+
+/// RAII guard returned by `DepGraph::enter`. Marks its node verified and
+/// pops it off the query stack on drop, regardless of which path the query
+/// takes to return (normal exit, an early `?`, or a panic).
+pub struct Enter<'a, K: 'a + Eq + Hash + Clone>(&'a DepGraph<K>);
+
+impl<'a, K: 'a + Eq + Hash + Clone> Drop for Enter<'a, K> {
+	fn drop(&mut self) {
+		self.0.leave();
+	}
+}
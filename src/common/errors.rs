@@ -0,0 +1,149 @@
+// Copyright (c) 2017 Fabian Schuiki
+
+//! Diagnostics emitted while compiling a design, and the builder used to
+//! assemble them before handing them to `Session::emit`.
+
+use std::fmt;
+use source::Span;
+
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+	Note,
+	Warning,
+	Error,
+	Fatal,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Severity::Note => write!(f, "note"),
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error => write!(f, "error"),
+			Severity::Fatal => write!(f, "fatal error"),
+		}
+	}
+}
+
+
+/// A secondary message attached to a diagnostic, pointing at whatever spans
+/// help explain the primary message (e.g. "previous declaration was here:").
+#[derive(Debug, Clone)]
+pub struct DiagNote {
+	message: String,
+	spans: Vec<Span>,
+}
+
+impl DiagNote {
+	/// The note's message.
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+
+	/// The spans this note points at, if any.
+	pub fn spans(&self) -> &[Span] {
+		&self.spans
+	}
+}
+
+
+/// A diagnostic being assembled. Build one with `DiagBuilder2::error` (or one
+/// of the other severity constructors), attach spans and notes, then hand it
+/// to `Session::emit`.
+#[derive(Debug, Clone)]
+pub struct DiagBuilder2 {
+	severity: Severity,
+	message: String,
+	spans: Vec<Span>,
+	notes: Vec<DiagNote>,
+}
+
+impl DiagBuilder2 {
+	/// Create a new diagnostic of the given severity.
+	pub fn new<S: Into<String>>(severity: Severity, message: S) -> DiagBuilder2 {
+		DiagBuilder2 {
+			severity: severity,
+			message: message.into(),
+			spans: Vec::new(),
+			notes: Vec::new(),
+		}
+	}
+
+	/// Create a new fatal diagnostic.
+	pub fn fatal<S: Into<String>>(message: S) -> DiagBuilder2 {
+		DiagBuilder2::new(Severity::Fatal, message)
+	}
+
+	/// Create a new error diagnostic.
+	pub fn error<S: Into<String>>(message: S) -> DiagBuilder2 {
+		DiagBuilder2::new(Severity::Error, message)
+	}
+
+	/// Create a new warning diagnostic.
+	pub fn warning<S: Into<String>>(message: S) -> DiagBuilder2 {
+		DiagBuilder2::new(Severity::Warning, message)
+	}
+
+	/// Create a new note-level diagnostic (as opposed to `add_note`, which
+	/// attaches a secondary note to an existing diagnostic).
+	pub fn note<S: Into<String>>(message: S) -> DiagBuilder2 {
+		DiagBuilder2::new(Severity::Note, message)
+	}
+
+	/// Attach a span. Before the first `add_note` this adds a primary span
+	/// pointing at the main message; once a note has been added, it attaches
+	/// to that note instead.
+	pub fn span(mut self, span: Span) -> DiagBuilder2 {
+		match self.notes.last_mut() {
+			Some(note) => note.spans.push(span),
+			None => self.spans.push(span),
+		}
+		self
+	}
+
+	/// Attach a secondary note with its own message. Any `span` calls that
+	/// follow attach to this note instead of the primary message, until the
+	/// next `add_note`.
+	pub fn add_note<S: Into<String>>(mut self, message: S) -> DiagBuilder2 {
+		self.notes.push(DiagNote{ message: message.into(), spans: Vec::new() });
+		self
+	}
+
+	/// This diagnostic's severity.
+	pub fn severity(&self) -> Severity {
+		self.severity
+	}
+
+	/// The primary message.
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+
+	/// The primary spans, i.e. those attached before the first `add_note`.
+	pub fn spans(&self) -> &[Span] {
+		&self.spans
+	}
+
+	/// The secondary notes, each with its own message and spans.
+	pub fn notes(&self) -> &[DiagNote] {
+		&self.notes
+	}
+}
+
+impl fmt::Display for DiagBuilder2 {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: {}", self.severity, self.message)?;
+		for span in &self.spans {
+			write!(f, "\n  {}", span.extract())?;
+		}
+		for note in &self.notes {
+			write!(f, "\n{}: {}", Severity::Note, note.message)?;
+			for span in &note.spans {
+				write!(f, "\n  {}", span.extract())?;
+			}
+		}
+		Ok(())
+	}
+}
@@ -13,13 +13,27 @@ pub mod source;
 pub mod grind;
 pub mod id;
 pub mod score;
+pub mod depgraph;
 
 pub use self::id::NodeId;
+use std::cell::RefCell;
 use errors::DiagBuilder2;
+use source::Span;
+use depgraph::DepGraph;
 
 
 pub struct Session {
 	pub opts: SessionOptions,
+	/// Every diagnostic emitted so far, in emission order. Lets a driver
+	/// query counts and severities after a compile instead of scraping
+	/// stdout for the rendered text.
+	diagnostics: RefCell<Vec<DiagBuilder2>>,
+	/// Tracks which queries a frontend's scoreboard read while computing
+	/// another, keyed by the generic `NodeId` shared across frontends.
+	/// Lets `invalidate` transitively flag every query that, directly or
+	/// indirectly, depended on a node that changed, instead of a frontend
+	/// having to flush its whole query cache on every change.
+	pub deps: DepGraph<NodeId>,
 }
 
 impl Session {
@@ -29,13 +43,36 @@ impl Session {
 			opts: SessionOptions {
 				ignore_duplicate_defs: false,
 				trace_scoreboard: false,
-			}
+				diagnostic_format: DiagnosticFormat::Human,
+			},
+			diagnostics: RefCell::new(Vec::new()),
+			deps: DepGraph::new(),
 		}
 	}
 
-	/// Emit a diagnostic.
+	/// Invalidate `node` and every query that transitively depended on it.
+	/// A frontend's scoreboard should call this whenever the source
+	/// underlying `node` changes, then evict the returned nodes from its
+	/// own memoized caches so the next `make`/`hir`/`ast` call recomputes
+	/// them instead of reusing a stale value.
+	pub fn invalidate(&self, node: NodeId) -> ::std::collections::HashSet<NodeId> {
+		self.deps.invalidate(node)
+	}
+
+	/// Emit a diagnostic, rendering it through the sink selected by
+	/// `opts.diagnostic_format` and recording it for later inspection via
+	/// `diagnostics()`.
 	pub fn emit(&self, err: DiagBuilder2) {
-		println!("{}", err);
+		match self.opts.diagnostic_format {
+			DiagnosticFormat::Human => println!("{}", err),
+			DiagnosticFormat::Json => println!("{}", render_diagnostic_json(&err)),
+		}
+		self.diagnostics.borrow_mut().push(err);
+	}
+
+	/// All diagnostics emitted on this session so far, in emission order.
+	pub fn diagnostics(&self) -> ::std::cell::Ref<Vec<DiagBuilder2>> {
+		self.diagnostics.borrow()
 	}
 }
 
@@ -44,4 +81,48 @@ pub struct SessionOptions {
 	pub ignore_duplicate_defs: bool,
 	/// Print a trace of scoreboard invocations for debugging purposes.
 	pub trace_scoreboard: bool,
+	/// How diagnostics emitted through `Session::emit` are rendered.
+	pub diagnostic_format: DiagnosticFormat,
+}
+
+
+/// Selects how `Session::emit` renders a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticFormat {
+	/// Human-readable text, as printed to a terminal.
+	Human,
+	/// A single-line, machine-readable JSON object, so editors and CI can
+	/// consume diagnostics without parsing free-form text.
+	Json,
+}
+
+
+/// Render a diagnostic as a single-line JSON object with `severity`,
+/// `message`, `spans`, and `notes` broken out as separate fields (rather
+/// than embedding the already-rendered human-readable text). Each span is
+/// rendered by `render_span_json`.
+fn render_diagnostic_json(err: &DiagBuilder2) -> String {
+	use rustc_serialize::json::Json;
+	let mut obj = ::std::collections::BTreeMap::new();
+	obj.insert("severity".to_string(), Json::String(err.severity().to_string()));
+	obj.insert("message".to_string(), Json::String(err.message().to_string()));
+	obj.insert("spans".to_string(), Json::Array(err.spans().iter().map(render_span_json).collect()));
+	obj.insert("notes".to_string(), Json::Array(err.notes().iter().map(|note| {
+		let mut note_obj = ::std::collections::BTreeMap::new();
+		note_obj.insert("message".to_string(), Json::String(note.message().to_string()));
+		note_obj.insert("spans".to_string(), Json::Array(note.spans().iter().map(render_span_json).collect()));
+		Json::Object(note_obj)
+	}).collect()));
+	Json::Object(obj).to_string()
+}
+
+/// Render a single span as a JSON object with `"text"`, the source text it
+/// covers, and `"location"`, the span's `Debug` output as a string. The file,
+/// byte range, line, and column are not broken out as separate fields.
+fn render_span_json(span: &Span) -> ::rustc_serialize::json::Json {
+	use rustc_serialize::json::Json;
+	let mut obj = ::std::collections::BTreeMap::new();
+	obj.insert("text".to_string(), Json::String(span.extract().to_string()));
+	obj.insert("location".to_string(), Json::String(format!("{:?}", span)));
+	Json::Object(obj)
 }
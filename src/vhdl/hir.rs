@@ -123,7 +123,7 @@ pub struct IntfSignal {
 }
 
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IntfSignalMode {
 	In,
 	Out,
@@ -133,6 +133,20 @@ pub enum IntfSignalMode {
 }
 
 
+/// The resolved signature of a subprogram, i.e. the information needed to
+/// tell overloaded functions, procedures, and operators apart. Enumeration
+/// literals do not store a `Signature`; when overloads are compared they are
+/// treated as zero-parameter functions returning their own type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+	/// The parameters, in declaration order, given by their mode and subtype
+	/// mark.
+	pub params: Vec<(IntfSignalMode, TypeMarkRef)>,
+	/// The return type. `None` for procedures.
+	pub return_ty: Option<TypeMarkRef>,
+}
+
+
 #[derive(Debug)]
 pub struct SubtypeInd {
 	/// The location within the source code.
@@ -197,6 +211,58 @@ pub struct Package {
 }
 
 
+/// A generic package instantiation as per IEEE 1076-2008 section 4.9.
+#[derive(Debug)]
+pub struct PkgInst {
+	/// The parent scope, i.e. the scope in which the instantiation appears.
+	pub parent: ScopeRef,
+	/// The name of the package instance.
+	pub name: Spanned<Name>,
+	/// The uninstantiated generic package being instantiated.
+	pub pkg: PkgDeclRef,
+	/// The generic map, associating each formal generic of `pkg`, in order,
+	/// with the actual supplied at the instantiation site.
+	pub generic_map: Vec<(GenericRef, GenericActual)>,
+}
+
+
+/// An actual supplied for a generic at a package instantiation.
+#[derive(Debug, Clone, Copy)]
+pub enum GenericActual {
+	/// A type actual, e.g. for a generic type or subtype.
+	Type(TypeMarkRef),
+	/// A constant actual, e.g. for a generic constant.
+	Expr(ExprRef),
+}
+
+
+/// A generic declared on a package or subprogram, as per IEEE 1076-2008
+/// section 6.5.6.
+#[derive(Debug)]
+pub struct Generic {
+	/// The parent scope.
+	pub parent: ScopeRef,
+	/// The name of the generic.
+	pub name: Spanned<Name>,
+	/// What kind of generic this is, and the information needed to check
+	/// that an actual conforms to it.
+	pub kind: GenericKind,
+}
+
+
+/// The kind of a `Generic`, distinguishing a generic type from a generic
+/// constant so that an actual supplied at an instantiation site can be
+/// checked to conform to the formal.
+#[derive(Debug, Clone, Copy)]
+pub enum GenericKind {
+	/// A generic type, e.g. `type T`. Only a `GenericActual::Type` conforms.
+	Type,
+	/// A generic constant of the given subtype, e.g. `constant C : natural`.
+	/// Only a `GenericActual::Expr` conforms.
+	Const(SubtypeIndRef),
+}
+
+
 #[derive(Debug)]
 pub struct TypeDecl {
 	/// The parent scope.
@@ -3,6 +3,7 @@
 //! This module implements the tracking of definitions and scopes for VHDL.
 
 use score::*;
+use moore_common::NodeId;
 
 
 macro_rules! impl_make_defs {
@@ -126,16 +127,98 @@ impl_make_defs!(self, id: CtxItemsRef => {
 
 
 // Definitions in an entity.
-impl_make_defs!(self, _id: EntityRef => {
-	// TODO: Implement this.
-	Ok(self.sb.arenas.defs.alloc(HashMap::new()))
+impl_make_defs!(self, id: EntityRef => {
+	let _entry = self.sess.deps.enter(id.into());
+	let hir = self.hir(id)?;
+	let mut names_and_defs = Vec::new();
+	for &id in &hir.generics {
+		self.sess.deps.record(id.into());
+		names_and_defs.push((self.ast(id).1.name.map_into(), Def::Generic(id)));
+	}
+	for &id in &hir.ports {
+		self.sess.deps.record(id.into());
+		let port = self.hir(id)?;
+		names_and_defs.push((port.name.map_into(), Def::Port(id)));
+	}
+
+	let mut defs = HashMap::new();
+	let mut had_fails = false;
+	for (name, def) in names_and_defs {
+		if self.sess.opts.trace_scoreboard { println!("[SB][VHDL][SCOPE] declaring `{}` as {:?}", name.value, def); }
+		if let Some(existing) = defs.insert(name.value, vec![Spanned::new(def, name.span)]) {
+			self.sess.emit(
+				DiagBuilder2::error(format!("`{}` has already been declared", name.value))
+				.span(name.span)
+				.add_note("previous declaration was here:")
+				.span(existing.last().unwrap().span)
+			);
+			had_fails = true;
+		}
+	}
+	if had_fails {
+		Err(())
+	} else {
+		Ok(self.sb.arenas.defs.alloc(defs))
+	}
 });
 
 
 // Definitions in an architecture.
-impl_make_defs!(self, _id: ArchRef => {
-	// TODO: Implement this.
-	Ok(self.sb.arenas.defs.alloc(HashMap::new()))
+impl_make_defs!(self, id: ArchRef => {
+	let _entry = self.sess.deps.enter(id.into());
+	let hir = self.hir(id)?;
+	let mut defs = HashMap::new();
+	let mut had_fails = false;
+	for decl in &hir.decls {
+		let names_and_defs = match *decl {
+			DeclInBlockRef::Signal(id) => { self.sess.deps.record(id.into()); vec![(self.ast(id).1.name.map_into(), Def::Signal(id))] }
+			DeclInBlockRef::Const(id) => { self.sess.deps.record(id.into()); vec![(self.ast(id).1.name.map_into(), Def::Const(id))] }
+			DeclInBlockRef::Variable(id) => { self.sess.deps.record(id.into()); vec![(self.ast(id).1.name.map_into(), Def::Var(id))] }
+			DeclInBlockRef::File(id) => { self.sess.deps.record(id.into()); vec![(self.ast(id).1.name.map_into(), Def::File(id))] }
+			DeclInBlockRef::Type(id) => {
+				self.sess.deps.record(id.into());
+				let hir = self.hir(id)?;
+				let mut v = vec![(hir.name.map_into(), Def::Type(id))];
+				match hir.data {
+					Some(hir::TypeData::Enum(_, ref lits)) => {
+						for (i, lit) in lits.iter().enumerate() {
+							match *lit {
+								hir::EnumLit::Ident(n) => v.push((n.map_into(), Def::Enum(EnumRef(id, i)))),
+								hir::EnumLit::Char(c) => v.push((c.map_into(), Def::Enum(EnumRef(id, i)))),
+							}
+						}
+					}
+					_ => ()
+				}
+				v
+			}
+			DeclInBlockRef::Subtype(id) => { self.sess.deps.record(id.into()); vec![(self.ast(id).1.name.map_into(), Def::Subtype(id))] }
+		};
+
+		for (name, def) in names_and_defs {
+			if self.sess.opts.trace_scoreboard { println!("[SB][VHDL][SCOPE] declaring `{}` as {:?}", name.value, def); }
+			match def {
+				// Handle overloadable cases.
+				Def::Enum(_) => defs.entry(name.value).or_insert_with(|| Vec::new()).push(Spanned::new(def, name.span)),
+
+				// Handle unique cases.
+				_ => if let Some(existing) = defs.insert(name.value, vec![Spanned::new(def, name.span)]) {
+					self.sess.emit(
+						DiagBuilder2::error(format!("`{}` has already been declared", name.value))
+						.span(name.span)
+						.add_note("previous declaration was here:")
+						.span(existing.last().unwrap().span)
+					);
+					had_fails = true;
+				}
+			}
+		}
+	}
+	if had_fails {
+		Err(())
+	} else {
+		Ok(self.sb.arenas.defs.alloc(defs))
+	}
 });
 
 
@@ -165,13 +248,31 @@ impl_make_defs!(self, id: PkgDeclRef => {
 				v
 			}
 			DeclInPkgRef::Subtype(id) => vec![(self.ast(id).1.name.map_into(), Def::Subtype(id))],
+			DeclInPkgRef::Subprog(id) => vec![(self.ast(id).1.name.map_into(), Def::Subprog(id, self.subprog_signature(id)?))],
 		};
 
 		for (name, def) in names_and_defs {
 			if self.sess.opts.trace_scoreboard { println!("[SB][VHDL][SCOPE] declaring `{}` as {:?}", name.value, def); }
 			match def {
-				// Handle overloadable cases.
-				Def::Enum(_) => defs.entry(name.value).or_insert_with(|| Vec::new()).push(Spanned::new(def, name.span)),
+				// Handle overloadable cases. Enumeration literals are
+				// zero-argument overloadable functions, and subprograms are
+				// overloadable by their full signature, so both are allowed
+				// to coexist under one name as long as no two of them share
+				// a homograph (the same name AND the same signature).
+				Def::Enum(_) | Def::Subprog(..) => {
+					let entry = defs.entry(name.value).or_insert_with(|| Vec::new());
+					if let Some(existing) = entry.iter().find(|d| is_homograph(&d.value, &def)) {
+						self.sess.emit(
+							DiagBuilder2::error(format!("`{}` has already been declared with this signature", name.value))
+							.span(name.span)
+							.add_note("previous declaration was here:")
+							.span(existing.span)
+						);
+						had_fails = true;
+					} else {
+						entry.push(Spanned::new(def, name.span));
+					}
+				}
 
 				// Handle unique cases.
 				_ => if let Some(existing) = defs.insert(name.value, vec![Spanned::new(def, name.span)]) {
@@ -195,12 +296,277 @@ impl_make_defs!(self, id: PkgDeclRef => {
 
 
 // Definitions in a package instance.
-impl_make_defs!(self, _id: PkgInstRef => {
-	// TODO: Implement this.
-	unimplemented!();
+impl_make_defs!(self, id: PkgInstRef => {
+	let _entry = self.sess.deps.enter(id.into());
+	let hir = self.hir(id)?;
+	self.sess.deps.record(hir.pkg.into());
+	let generic_pkg_hir = self.hir(hir.pkg)?;
+	let generic_pkg_defs = self.make(hir.pkg)?;
+
+	// Check that the instantiation supplies exactly the generics the
+	// package declares.
+	if hir.generic_map.len() != generic_pkg_hir.generics.len() {
+		self.sess.emit(
+			DiagBuilder2::error(format!("wrong number of generics for instantiation of `{}`: expected {}, got {}",
+				hir.name.value, generic_pkg_hir.generics.len(), hir.generic_map.len()))
+			.span(hir.name.span)
+		);
+		return Err(());
+	}
+
+	// Check that every actual conforms to the kind of its formal (a type
+	// actual for a generic type, a constant actual for a generic
+	// constant), then build the substitution from each formal generic to
+	// its actual.
+	let mut had_fails = false;
+	for &(generic, actual) in &hir.generic_map {
+		self.sess.deps.record(generic.into());
+		let generic_hir = self.hir(generic)?;
+		let conforms = match (generic_hir.kind, actual) {
+			(hir::GenericKind::Type, GenericActual::Type(_)) => true,
+			(hir::GenericKind::Const(_), GenericActual::Expr(_)) => true,
+			_ => false,
+		};
+		if !conforms {
+			self.sess.emit(
+				DiagBuilder2::error(format!("actual does not conform to the formal interface of generic `{}`", generic_hir.name.value))
+				.span(generic_hir.name.span)
+			);
+			had_fails = true;
+		}
+	}
+	if had_fails {
+		return Err(());
+	}
+	let subst: HashMap<GenericRef, GenericActual> = hir.generic_map.iter().cloned().collect();
+
+	// Materialize the instance's definitions by rewriting every
+	// declaration inherited from the generic package through the
+	// substitution, e.g. redirecting a function parameter typed by a
+	// formal generic type to the actual type supplied here.
+	let mut defs = HashMap::with_capacity(generic_pkg_defs.len());
+	for (name, list) in generic_pkg_defs {
+		let mut substituted = Vec::with_capacity(list.len());
+		for d in list {
+			substituted.push(Spanned::new(self.substitute_generics(d.value.clone(), &subst)?, d.span));
+		}
+		defs.insert(*name, substituted);
+	}
+
+	Ok(self.sb.arenas.defs.alloc(defs))
 });
 
 
+// A type mark always names either a type or a subtype declaration, so it
+// converts directly into the `Def` for whichever of the two it is.
+impl From<TypeMarkRef> for Def {
+	fn from(ty: TypeMarkRef) -> Def {
+		match ty {
+			TypeMarkRef::Type(id) => Def::Type(id),
+			TypeMarkRef::Subtype(id) => Def::Subtype(id),
+		}
+	}
+}
+
+
+// Resolve `ty` to the actual type supplied for it if it names one of the
+// generics in `subst`; otherwise return `ty` unchanged.
+fn substitute_type_mark(ty: TypeMarkRef, subst: &HashMap<GenericRef, GenericActual>) -> TypeMarkRef {
+	for (generic, actual) in subst {
+		if TypeMarkRef::from(*generic) == ty {
+			if let GenericActual::Type(actual_ty) = *actual {
+				return actual_ty;
+			}
+		}
+	}
+	ty
+}
+
+
+// Two definitions are homographs, and therefore conflict, if they share the
+// same name and the same signature. Enumeration literals are zero-argument
+// overloadable functions, so an enum literal's signature is an empty
+// parameter list returning its owning type (`EnumRef`'s `TypeDeclRef`).
+// Distinct enum types declaring a literal of the same name (e.g. two
+// enumeration types each with an `IDLE` literal) therefore do not conflict,
+// and neither does an enum literal clashing by name with an unrelated
+// subprogram whose signature plainly differs. Only `Def::Enum` and
+// `Def::Subprog` are overloadable at all; if the name is already held by
+// any other kind of definition, the pair always counts as a conflict.
+fn is_homograph(a: &Def, b: &Def) -> bool {
+	match (a, b) {
+		(&Def::Subprog(_, ref sig_a), &Def::Subprog(_, ref sig_b)) => sig_a == sig_b,
+		(&Def::Enum(EnumRef(ty_a, _)), &Def::Enum(EnumRef(ty_b, _))) => ty_a == ty_b,
+		(&Def::Enum(EnumRef(ty, _)), &Def::Subprog(_, ref sig)) |
+		(&Def::Subprog(_, ref sig), &Def::Enum(EnumRef(ty, _))) => {
+			sig.params.is_empty() && sig.return_ty == Some(TypeMarkRef::from(ty))
+		}
+		_ => true,
+	}
+}
+
+
+// The `NodeId` a use clause's dependency tracking should record for `def`,
+// i.e. the design unit that would need to be invalidated for `def` to
+// change. `None` for defs that name something the `DepGraph` does not
+// track on its own (e.g. builtins), or that are not reachable through a
+// use clause in the first place.
+fn def_dep_node(def: &Def) -> Option<NodeId> {
+	match *def {
+		Def::Lib(id) => Some(id.into()),
+		Def::Pkg(id) => Some(id.into()),
+		Def::PkgInst(id) => Some(id.into()),
+		Def::Type(id) => Some(id.into()),
+		Def::Subtype(id) => Some(id.into()),
+		_ => None,
+	}
+}
+
+
+impl<'sb, 'ast, 'ctx> ScoreContext<'sb, 'ast, 'ctx> {
+	// Rewrite `def`, a declaration inherited from an uninstantiated generic
+	// package, by replacing any reference to one of that package's formal
+	// generics with the actual supplied at the instantiation site
+	// described by `subst`.
+	//
+	// A subprogram's signature is carried inline in `Def::Subprog`, so its
+	// parameter/return types are substituted directly. An unconstrained
+	// subtype whose type mark names a formal generic (e.g. `subtype my_t
+	// is T;`) is entirely a synonym for that generic, so once substituted
+	// it becomes a synonym for the actual instead -- we redirect the
+	// definition itself to whatever the actual type mark resolves to,
+	// rather than allocating a new `SubtypeDecl` that still points at the
+	// formal. A *constrained* subtype (`subtype my_t is T range 0 to
+	// 10;`) cannot be redirected this way without silently dropping its
+	// constraint, so it is inherited unchanged and still refers to the
+	// formal.
+	//
+	// Every other definition (types, enumeration literals, nested packages
+	// and package instances) is inherited unchanged.
+	fn substitute_generics(&self, def: Def, subst: &HashMap<GenericRef, GenericActual>) -> Result<Def> {
+		Ok(match def {
+			Def::Subprog(id, sig) => Def::Subprog(id, hir::Signature{
+				params: sig.params.iter().map(|&(mode, ty)| (mode, substitute_type_mark(ty, subst))).collect(),
+				return_ty: sig.return_ty.map(|ty| substitute_type_mark(ty, subst)),
+			}),
+			Def::Subtype(id) => {
+				let subty_hir = self.hir(id)?;
+				let ind = self.hir(subty_hir.subty)?;
+				match ind.constraint {
+					hir::Constraint::None => {
+						let new_ty = substitute_type_mark(ind.type_mark.value, subst);
+						if new_ty == ind.type_mark.value {
+							def
+						} else {
+							Def::from(new_ty)
+						}
+					}
+					_ => def,
+				}
+			}
+			other => other,
+		})
+	}
+
+	// Determine the signature of a subprogram declaration, i.e. the ordered
+	// list of its parameter modes and subtype marks plus its optional
+	// return type. Used to tell overloaded subprograms apart.
+	fn subprog_signature(&self, id: SubprogRef) -> Result<hir::Signature> {
+		let hir = self.hir(id)?;
+		Ok(hir::Signature{
+			params: hir.params.iter().map(|&p| {
+				let param = self.hir(p)?;
+				Ok((param.mode, param.ty))
+			}).collect::<Result<Vec<_>>>()?,
+			return_ty: hir.return_ty,
+		})
+	}
+
+	/// Resolve `name` to the one subprogram (or overloaded enum literal)
+	/// among `candidates` whose signature matches `args`, the types of the
+	/// arguments at the call site. If more than one candidate matches
+	/// `args` alone -- e.g. two zero-argument enum literals of different
+	/// owning types, or two functions overloaded only by their return
+	/// type -- `expected_ty`, the type mark the result is expected to
+	/// conform to, narrows the match further; pass `None` where the
+	/// calling context does not constrain the result type. Emits an
+	/// "ambiguous" diagnostic listing every matching candidate's span if
+	/// more than one survives even after narrowing, and a "no matching
+	/// subprogram" diagnostic if none do.
+	pub fn resolve_overload(&self, name: Spanned<Name>, candidates: &[Spanned<Def>], args: &[TypeMarkRef], expected_ty: Option<TypeMarkRef>) -> Result<Def> {
+		let matches: Vec<_> = candidates.iter().filter(|d| match d.value {
+			Def::Subprog(_, ref sig) => sig.params.iter().map(|&(_, ty)| ty).eq(args.iter().cloned()),
+			Def::Enum(_) => args.is_empty(),
+			_ => false,
+		}).collect();
+
+		// If the arguments alone leave more than one candidate standing,
+		// try to break the tie using the expected result type. Only
+		// narrow down to candidates whose return type actually conforms;
+		// if none do (or there is no expected type to narrow by), fall
+		// back to the unnarrowed set so the diagnostics below stay
+		// accurate.
+		let matches = if matches.len() > 1 {
+			if let Some(expected_ty) = expected_ty {
+				let narrowed: Vec<_> = matches.iter().cloned().filter(|d| match d.value {
+					Def::Subprog(_, ref sig) => sig.return_ty == Some(expected_ty),
+					Def::Enum(EnumRef(ty, _)) => TypeMarkRef::from(ty) == expected_ty,
+					_ => false,
+				}).collect();
+				if !narrowed.is_empty() {
+					narrowed
+				} else {
+					matches
+				}
+			} else {
+				matches
+			}
+		} else {
+			matches
+		};
+
+		match matches.len() {
+			1 => Ok(matches[0].value),
+			0 => {
+				self.sess.emit(
+					DiagBuilder2::error(format!("no matching subprogram `{}` for the given arguments", name.value))
+					.span(name.span)
+				);
+				Err(())
+			}
+			_ => {
+				let mut d = DiagBuilder2::error(format!("`{}` is ambiguous", name.value)).span(name.span);
+				for m in &matches {
+					d = d.add_note("candidate declared here:").span(m.span);
+				}
+				self.sess.emit(d);
+				Err(())
+			}
+		}
+	}
+
+	/// Invalidate `node`, e.g. because the source text it was parsed from
+	/// changed. Transitively flags every query that directly or indirectly
+	/// read `node` as unverified in `self.sess.deps`.
+	///
+	/// `make_ctx_items_scope` below is the only query in this module that
+	/// consults `is_verified` itself (it owns its cache, `scope_table`,
+	/// directly), so this call has real, observable effect there: a
+	/// subsequent `make_ctx_items_scope` on a context-items scope that
+	/// read `node` recomputes instead of reusing the stale entry.
+	///
+	/// The `EntityRef`/`ArchRef`/`PkgInstRef` defs queries above also record
+	/// their reads into the same `DepGraph`; the `PkgDeclRef` defs query and
+	/// the scope queries record none. All of them are dispatched through
+	/// `impl_make!`, whose own memoization (outside this module) does not
+	/// consult `is_verified`, so invalidating a node they depend on flags it
+	/// here but does not make `self.make(id)` recompute it.
+	pub fn invalidate(&self, node: NodeId) {
+		self.sess.deps.invalidate(node);
+	}
+}
+
+
 // Populate a scope.
 impl_make_scope!(self, id: ScopeRef => {
 	match id {
@@ -230,71 +596,201 @@ impl_make_scope!(self, id: LibRef => {
 impl<'sb, 'ast, 'ctx> ScoreContext<'sb, 'ast, 'ctx> {
 	// Populate the scope of the context items that appear before a design unit. The
 	// scope of the design unit itself is a subscope of the context items.
+	//
+	// Context items are resolved incrementally: a scope is registered in
+	// `scope_table` up front, empty, and each `LibClause` or `UseClause`
+	// folds its contribution into `defs`/`explicit_defs`, which are then
+	// used to rebuild and re-register the scope before the next item is
+	// resolved. (`Scope` has no interior mutability, so once it is
+	// registered we cannot keep mutating the same allocation in place --
+	// rebuilding and re-inserting it is the straightforward alternative.)
+	// This way a `UseClause` only ever observes (1) the enclosing library
+	// scope via `parent`, (2) library names made visible by earlier
+	// `LibClause`s, and (3) `explicit_defs` contributed by earlier
+	// `UseClause`s -- never its own output, which is what used to make name
+	// resolution here loop forever.
+	//
+	// Unlike the defs/scope queries above, this one is a bespoke `pub fn`
+	// rather than an `impl_make!`-dispatched query, so it owns its cache
+	// (`scope_table`) outright -- which means it can actually consult
+	// `is_verified` before doing any work, making `ScoreContext::invalidate`
+	// have real effect here rather than just updating bookkeeping no one
+	// reads.
 	pub fn make_ctx_items_scope(&self, id: CtxItemsRef, parent: Option<ScopeRef>) -> Result<CtxItemsRef> {
+		if self.sess.deps.is_verified(&id.into()) && self.sb.scope_table.borrow().contains_key(&id.into()) {
+			return Ok(id);
+		}
+		let _entry = self.sess.deps.enter(id.into());
 		let (_, items) = self.ast(id);
 		let mut defs = Vec::new();
 		let mut explicit_defs = HashMap::new();
-		defs.push(id.into());
+		self.update_ctx_items_scope(id, parent, &defs, &explicit_defs);
+
+		// Use clauses that could not be resolved yet, e.g. because they
+		// depend on a later use clause. Retried after every subsequent item
+		// and drained to a fixed point once all items have been seen.
+		let mut pending = Vec::new();
+
 		for item in items {
-			if let &ast::CtxItem::UseClause(Spanned{value: ref names, ..}) = item {
-				for name in names {
-					// TODO: This creates an infinite loop, since the name lookup requires the context items to be ready.
-					let (res_name, mut out_defs, valid_span, mut tail) = self.resolve_compound_name(name, id.into(), true)?;
-					println!("resolving use clause {:?}", name);
-
-					// Resolve the optional `all`.
-					match tail.first() {
-						Some(&ast::NamePart::SelectAll(all_span)) => {
-							tail = &tail[1..];
-							match out_defs.pop() {
-								Some(Spanned{value: Def::Pkg(id), ..}) => {
-									defs.push(id.into());
-								}
-								Some(_) => {
-									self.sess.emit(
-										DiagBuilder2::error(format!("`all` not possible on `{}`", valid_span.extract()))
-										.span(all_span)
-									);
-									continue;
-								}
-								None => unreachable!()
+			match *item {
+				ast::CtxItem::LibClause(Spanned{ value: ref names, .. }) => {
+					for ident in names {
+						if let Some(&lib_id) = self.sb.lib_names.borrow().get(&ident.name) {
+							self.sess.deps.record(lib_id.into());
+							let slot = explicit_defs.entry(ident.name.into()).or_insert_with(|| Vec::new());
+							if !slot.is_empty() {
+								self.sess.emit(
+									DiagBuilder2::error(format!("`{}` has already been declared", ident.name))
+									.span(ident.span)
+								);
+							} else {
+								slot.push(Spanned::new(Def::Lib(lib_id), ident.span));
 							}
+						} else {
+							self.sess.emit(
+								DiagBuilder2::error(format!("no library named `{}` found", ident.name))
+								.span(ident.span)
+							);
 						}
-						_ => {
-							explicit_defs.entry(res_name).or_insert_with(|| Vec::new()).extend(out_defs);
-						}
 					}
-					println!("yields explicit_defs {:?}", explicit_defs);
+					self.update_ctx_items_scope(id, parent, &defs, &explicit_defs);
+				}
+				ast::CtxItem::UseClause(Spanned{value: ref names, ..}) => {
+					for name in names {
+						pending.push(name);
+					}
+				}
+			}
+			self.drain_pending_use_clauses(id, parent, &mut defs, &mut explicit_defs, &mut pending);
+		}
+
+		// One more drain over whatever is still pending. The loop above
+		// already drains to a fixed point after every item, so this pass
+		// resolves nothing new; what remains is reported below.
+		self.drain_pending_use_clauses(id, parent, &mut defs, &mut explicit_defs, &mut pending);
+
+		if !pending.is_empty() {
+			for name in &pending {
+				self.sess.emit(
+					DiagBuilder2::error("unresolvable use clause")
+					.span(name.span)
+				);
+			}
+			return Err(());
+		}
+
+		Ok(id)
+	}
+
+	// (Re-)allocate the scope for `id` from `defs`/`explicit_defs` as they
+	// stand right now and register it in `scope_table`, overwriting
+	// whatever was registered for `id` before.
+	fn update_ctx_items_scope(&self, id: CtxItemsRef, parent: Option<ScopeRef>, defs: &Vec<ScopeRef>, explicit_defs: &HashMap<ResolvableName, Vec<Spanned<Def>>>) {
+		let scope = self.sb.arenas.scope.alloc(Scope{
+			parent: parent,
+			defs: defs.clone(),
+			explicit_defs: explicit_defs.clone(),
+		});
+		self.sb.scope_table.borrow_mut().insert(id.into(), &*scope);
+	}
+
+	// Try to resolve as many of `pending`'s use clauses as possible against
+	// the scope described by `defs`/`explicit_defs` as it stands right now,
+	// folding whatever resolves into them, re-registering the scope after
+	// each bit of progress, and repeating until a full pass over the
+	// remainder makes no further progress. Clauses that still cannot be
+	// resolved are left in `pending` for a later attempt.
+	//
+	// Resolution is attempted silently here: a use clause that depends on
+	// one processed later (the "mutually dependent" case) will fail on
+	// every pass before the one where it finally succeeds, and reporting a
+	// "not found" diagnostic for each of those failed attempts would be
+	// bogus, since `Session::emit` prints immediately and cannot retract
+	// it. Only clauses still unresolved once the whole context item list
+	// has been drained are worth reporting, which `make_ctx_items_scope`
+	// does once via a single "unresolvable use clause" diagnostic.
+	fn drain_pending_use_clauses<'a>(&self, id: CtxItemsRef, parent: Option<ScopeRef>, defs: &mut Vec<ScopeRef>, explicit_defs: &mut HashMap<ResolvableName, Vec<Spanned<Def>>>, pending: &mut Vec<&'a ast::CompoundName>) {
+		let mut progress = true;
+		while progress && !pending.is_empty() {
+			progress = false;
+			let mut still_pending = Vec::new();
+			for name in pending.drain(..) {
+				if self.resolve_use_clause(id, defs, explicit_defs, name).is_ok() {
+					progress = true;
+				} else {
+					still_pending.push(name);
+				}
+			}
+			*pending = still_pending;
+			if progress {
+				self.update_ctx_items_scope(id, parent, defs, explicit_defs);
+			}
+		}
+	}
 
-					// Ensure that there is no garbage.
-					if tail.len() > 0 {
-						let span = Span::union(valid_span.end().into(), name.span.end());
+	// Resolve a single name from a use clause against the scope described
+	// by `defs`/`explicit_defs` and, on success, fold the result into them.
+	// Returns `Err(())` if the name could not be resolved yet, e.g. because
+	// it depends on a use clause that has not been processed yet; this is
+	// resolved silently (see `drain_pending_use_clauses`) so a forward
+	// reference does not spuriously report "not found" before it finally
+	// succeeds.
+	fn resolve_use_clause(&self, id: CtxItemsRef, defs: &mut Vec<ScopeRef>, explicit_defs: &mut HashMap<ResolvableName, Vec<Spanned<Def>>>, name: &ast::CompoundName) -> Result<()> {
+		let (res_name, mut out_defs, valid_span, mut tail) = self.resolve_compound_name(name, id.into(), false)?;
+
+		// Record a dependency on whatever the use clause named, so
+		// invalidating one of those design units also invalidates the
+		// context-items scopes that opened it.
+		for def in &out_defs {
+			if let Some(node) = def_dep_node(&def.value) {
+				self.sess.deps.record(node);
+			}
+		}
+
+		// Resolve the optional `all`.
+		match tail.first() {
+			Some(&ast::NamePart::SelectAll(all_span)) => {
+				tail = &tail[1..];
+				match out_defs.pop() {
+					Some(Spanned{value: Def::Pkg(id), ..}) => {
+						defs.push(id.into());
+					}
+					Some(_) => {
 						self.sess.emit(
-							DiagBuilder2::error("invalid name suffix")
-							.span(span)
+							DiagBuilder2::error(format!("`all` not possible on `{}`", valid_span.extract()))
+							.span(all_span)
 						);
-						continue;
+						return Ok(());
 					}
+					None => unreachable!()
 				}
 			}
+			_ => {
+				explicit_defs.entry(res_name).or_insert_with(|| Vec::new()).extend(out_defs);
+			}
 		}
-		self.sb.scope_table.borrow_mut().insert(id.into(), self.sb.arenas.scope.alloc(Scope{
-			parent: parent,
-			defs: defs,
-			explicit_defs: explicit_defs,
-		}));
-		Ok(id)
+
+		// Ensure that there is no garbage.
+		if tail.len() > 0 {
+			let span = Span::union(valid_span.end().into(), name.span.end());
+			self.sess.emit(
+				DiagBuilder2::error("invalid name suffix")
+				.span(span)
+			);
+		}
+
+		Ok(())
 	}
 }
 
 
-// Populate the scope of an entity.
+// Populate the scope of an entity. Its own declarative region (generics and
+// ports, via the `EntityRef` defs above) sits over the shared context-items
+// scope, which already accounts for any use clauses.
 impl_make_scope!(self, id: EntityRef => {
 	let hir = self.hir(id)?;
 	let mut defs = Vec::new();
 	defs.push(id.into());
-	// TODO: Resolve use clauses and add whatever they bring into scope to
-	// the defs array.
 	let parent = self.make_ctx_items_scope(hir.ctx_items, None)?;
 	Ok(self.sb.arenas.scope.alloc(Scope{
 		parent: Some(parent.into()),
@@ -304,13 +800,14 @@ impl_make_scope!(self, id: EntityRef => {
 });
 
 
-// Populate the scope of an architecture.
+// Populate the scope of an architecture. Its own declarative region (the
+// `ArchRef` defs above) sits over the architecture's own context items,
+// whose parent is the entity's declarative region, so a port declared on
+// the entity resolves from inside the architecture body.
 impl_make_scope!(self, id: ArchRef => {
 	let hir = self.hir(id)?;
 	let mut defs = Vec::new();
 	defs.push(id.into());
-	// TODO: Resolve use clauses and add whatever they bring into scope to
-	// the defs array.
 	let parent = self.make_ctx_items_scope(hir.ctx_items, Some(hir.entity.into()))?;
 	Ok(self.sb.arenas.scope.alloc(Scope{
 		parent: Some(parent.into()),
@@ -340,7 +837,13 @@ impl_make_scope!(self, id: PkgDeclRef => {
 
 
 // Populate the scope of a package instance.
-impl_make_scope!(self, _id: PkgInstRef => {
-	// TODO: Implement this.
-	unimplemented!();
+impl_make_scope!(self, id: PkgInstRef => {
+	let hir = self.hir(id)?;
+	let mut defs = Vec::new();
+	defs.push(id.into());
+	Ok(self.sb.arenas.scope.alloc(Scope{
+		parent: Some(hir.parent),
+		defs: defs,
+		explicit_defs: HashMap::new(),
+	}))
 });